@@ -0,0 +1,44 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use crate::quotes::QuoteProvider;
+
+pub struct CurrencyConverter {
+    base_currency: String,
+    rates: HashMap<String, Decimal>,
+    provider: Option<Box<dyn QuoteProvider>>,
+}
+
+impl CurrencyConverter {
+    pub fn new(
+        base_currency: String,
+        rates: HashMap<String, Decimal>,
+        provider: Option<Box<dyn QuoteProvider>>,
+    ) -> Self {
+        Self {
+            base_currency,
+            rates,
+            provider,
+        }
+    }
+
+    fn rate_to_base(&self, currency: &str) -> anyhow::Result<Decimal> {
+        if currency == self.base_currency {
+            return Ok(dec!(1.0));
+        }
+        if let Some(rate) = self.rates.get(currency) {
+            return Ok(*rate);
+        }
+        let provider = self.provider.as_deref().ok_or_else(|| {
+            anyhow!("no FX rate for {currency} in [rates] and no [quotes] section or QUOTE_PROVIDER env var to resolve one")
+        })?;
+        crate::quotes::resolve_fx_rate(provider, currency, &self.base_currency)
+    }
+
+    pub fn to_base(&self, currency: &str, amount: Decimal) -> anyhow::Result<Decimal> {
+        Ok(amount * self.rate_to_base(currency)?)
+    }
+}
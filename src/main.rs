@@ -1,7 +1,10 @@
+use std::collections::HashMap;
 use std::io;
 
 use clap::{Arg, Command};
 use portfolio::{Action, BuySell};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
 use termion::event::Key;
 use termion::input::MouseTerminal;
 use termion::raw::IntoRawMode;
@@ -13,7 +16,10 @@ use tui::text::{Line, Span};
 use tui::widgets::{BarChart, Block, Borders, Paragraph, Tabs};
 use tui::Terminal;
 
+mod currency;
+mod performance;
 mod portfolio;
+mod quotes;
 mod tuiutil;
 
 use crate::tuiutil::event::{Event, Events};
@@ -23,21 +29,100 @@ struct TuiApp<'a> {
     tabs: TabsState<'a>,
 }
 
+fn get_pinned_assets_line(target_portfolio: &portfolio::Portfolio) -> Option<Line> {
+    let pinned: Vec<&str> = target_portfolio
+        .assets
+        .iter()
+        .filter(|a| a.pinned)
+        .map(|a| a.name.as_str())
+        .collect();
+    if pinned.is_empty() {
+        None
+    } else {
+        Some(Line::from(Span::styled(
+            format!("Within band, left untouched: {}\n", pinned.join(", ")),
+            Style::default().fg(Color::Cyan),
+        )))
+    }
+}
+
+fn get_tax_impact_to_display(actions: &[Action]) -> Vec<Line> {
+    let mut ret = Vec::new();
+    // Realized gains are in each asset's own currency, so the running total
+    // is tracked per currency rather than pooled across them.
+    let mut running_totals: HashMap<String, Decimal> = HashMap::new();
+    for a in actions {
+        let BuySell::Sell = a.buysell else {
+            continue;
+        };
+        let Some(gain) = a.realized_gain else {
+            continue;
+        };
+        let running_total = running_totals.entry(a.currency.clone()).or_insert(dec!(0.0));
+        *running_total += gain;
+        let color = if gain >= dec!(0.0) {
+            Color::Green
+        } else {
+            Color::Red
+        };
+        ret.push(Line::from(Span::styled(
+            format!(
+                "SELL {} {} realized {:.2}{} (running total {:.2}{})\n",
+                a.amount.normalize(),
+                a.name,
+                gain,
+                a.currency,
+                running_total,
+                a.currency
+            ),
+            Style::default().fg(color),
+        )));
+    }
+    if ret.is_empty() {
+        ret.push(Line::from("No sells with lot data to report."));
+    }
+    ret
+}
+
+fn get_performance_to_display(original_portfolio: &portfolio::Portfolio) -> anyhow::Result<Vec<Line>> {
+    let irr = original_portfolio.annualized_return()?;
+    let contributions = original_portfolio.total_contributions();
+    let gain = original_portfolio.absolute_gain();
+    let base = &original_portfolio.base_currency;
+    let gain_color = if gain >= dec!(0.0) {
+        Color::Green
+    } else {
+        Color::Red
+    };
+    Ok(vec![
+        Line::from(Span::styled(
+            format!("Annualized return (IRR): {irr:.2}%\n"),
+            Style::default().fg(Color::Cyan),
+        )),
+        Line::from(format!("Total contributions: {contributions:.2}{base}\n")),
+        Line::from(Span::styled(
+            format!("Absolute gain: {gain:.2}{base}\n"),
+            Style::default().fg(gain_color),
+        )),
+    ])
+}
+
 fn get_actions_to_display(actions: &[Action]) -> Vec<Line> {
     let mut ret = Vec::new();
     for a in actions {
+        let amount = a.amount.normalize();
         match a.buysell {
             BuySell::Buy => ret.push(Line::from(Span::styled(
                 format!(
-                    "{} {} {} -{:.2}$\n",
-                    "BUY", a.amount, a.name, a.transaction_value
+                    "{} {} {} -{:.2}{} (fee {:.2}{})\n",
+                    "BUY", amount, a.name, a.transaction_value, a.currency, a.commission, a.currency
                 ),
                 Style::default().fg(Color::Red),
             ))),
             BuySell::Sell => ret.push(Line::from(Span::styled(
                 format!(
-                    "{} {} {} +{:.2}$\n",
-                    "SELL", a.amount, a.name, a.transaction_value
+                    "{} {} {} +{:.2}{} (fee {:.2}{})\n",
+                    "SELL", amount, a.name, a.transaction_value, a.currency, a.commission, a.currency
                 ),
                 Style::default().fg(Color::Green),
             ))),
@@ -59,19 +144,24 @@ fn main() -> anyhow::Result<()> {
     match load_res {
         Ok(mut original_portfolio) => {
             // println!("Original {:?}", _original_portfolio);
+            let converter = original_portfolio.currency_converter()?;
             let mut target_portfolio = if original_portfolio.donotsell {
-                original_portfolio.add_without_selling()
+                original_portfolio.add_without_selling(&converter)?
             } else {
-                original_portfolio.rebalance()
+                original_portfolio.rebalance(&converter)?
             };
             // println!("Rebalanced {:?}", _target_portfolio);
 
-            let actions = original_portfolio.get_actions(&target_portfolio);
+            let actions = original_portfolio.get_actions(&target_portfolio)?;
             // println!("Actions {:?}", _actions);
-            let display_actions = get_actions_to_display(&actions);
+            let mut display_actions = Vec::new();
+            display_actions.extend(get_pinned_assets_line(&target_portfolio));
+            display_actions.extend(get_actions_to_display(&actions));
+            let tax_impact_display = get_tax_impact_to_display(&actions);
+            let performance_display = get_performance_to_display(&original_portfolio)?;
 
-            let original_alloc_data: Vec<(&str, u64)> = original_portfolio.get_display_data();
-            let target_alloc_data: Vec<(&str, u64)> = target_portfolio.get_display_data();
+            let original_alloc_data: Vec<(&str, u64)> = original_portfolio.get_display_data()?;
+            let target_alloc_data: Vec<(&str, u64)> = target_portfolio.get_display_data()?;
 
             let stdout = io::stdout().into_raw_mode()?;
             let stdout = MouseTerminal::from(stdout);
@@ -84,7 +174,12 @@ fn main() -> anyhow::Result<()> {
 
             // App
             let mut app = TuiApp {
-                tabs: TabsState::new(vec!["Original/New Allocations", "Actions"]),
+                tabs: TabsState::new(vec![
+                    "Original/New Allocations",
+                    "Actions",
+                    "Tax Impact",
+                    "Performance",
+                ]),
             };
 
             // Main loop
@@ -152,6 +247,24 @@ fn main() -> anyhow::Result<()> {
                                 .alignment(Alignment::Left);
                             f.render_widget(p, chunks[1]);
                         }
+                        2 => {
+                            let block = Block::default()
+                                .borders(Borders::ALL)
+                                .title("Tax Impact");
+                            let p = Paragraph::new(tax_impact_display.clone())
+                                .block(block)
+                                .alignment(Alignment::Left);
+                            f.render_widget(p, chunks[1]);
+                        }
+                        3 => {
+                            let block = Block::default()
+                                .borders(Borders::ALL)
+                                .title("Performance");
+                            let p = Paragraph::new(performance_display.clone())
+                                .block(block)
+                                .alignment(Alignment::Left);
+                            f.render_widget(p, chunks[1]);
+                        }
                         _ => {}
                     }
                 })?;
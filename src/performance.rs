@@ -0,0 +1,117 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::anyhow;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use crate::portfolio::Cashflow;
+
+const SECONDS_PER_DAY: u64 = 60 * 60 * 24;
+const BINARY_SEARCH_LOW: Decimal = dec!(-0.99);
+const BINARY_SEARCH_HIGH: Decimal = dec!(10.0);
+const BINARY_SEARCH_ITERATIONS: u32 = 50;
+const DAYS_PER_YEAR: Decimal = dec!(365.0);
+
+// Howard Hinnant's days_from_civil algorithm, to avoid a date/time crate.
+fn days_since_epoch(date: &str) -> anyhow::Result<i64> {
+    let parts: Vec<&str> = date.split('-').collect();
+    let [y, m, d] = parts[..] else {
+        return Err(anyhow!("invalid date {date}, expected YYYY-MM-DD"));
+    };
+    let y: i64 = y.parse()?;
+    let m: i64 = m.parse()?;
+    let d: i64 = d.parse()?;
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let mp = if m > 2 { m - 3 } else { m + 9 };
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    Ok(era * 146097 + doe - 719468)
+}
+
+fn today_epoch_day() -> anyhow::Result<i64> {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    Ok((secs / SECONDS_PER_DAY) as i64)
+}
+
+// Emulates a daily-compounding deposit seeded by `flows` (sorted, (day,
+// amount)) at candidate rate `annual_rate`, returning its balance on
+// `value_day`.
+fn emulate_deposit(flows: &[(i64, Decimal)], annual_rate: Decimal, value_day: i64) -> Decimal {
+    let daily_rate = annual_rate / DAYS_PER_YEAR;
+    let mut balance = dec!(0.0);
+    let mut remaining = flows.iter().peekable();
+    let start_day = flows.first().map_or(value_day, |(day, _)| *day);
+    for day in start_day..=value_day {
+        while let Some((flow_day, amount)) = remaining.peek() {
+            if *flow_day == day {
+                balance += *amount;
+                remaining.next();
+            } else {
+                break;
+            }
+        }
+        if day != value_day {
+            balance *= dec!(1.0) + daily_rate;
+        }
+    }
+    balance
+}
+
+// Binary-searches the annual rate of an emulated deposit until its balance
+// matches `current_value`.
+pub fn annualized_return(cashflows: &[Cashflow], current_value: Decimal) -> anyhow::Result<Decimal> {
+    if cashflows.is_empty() {
+        return Ok(dec!(0.0));
+    }
+    let mut flows: Vec<(i64, Decimal)> = cashflows
+        .iter()
+        .map(|c| Ok((days_since_epoch(&c.date)?, c.amount)))
+        .collect::<anyhow::Result<_>>()?;
+    flows.sort_by_key(|(day, _)| *day);
+    let value_day = today_epoch_day()?;
+
+    let mut low = BINARY_SEARCH_LOW;
+    let mut high = BINARY_SEARCH_HIGH;
+    for _ in 0..BINARY_SEARCH_ITERATIONS {
+        let mid = (low + high) / dec!(2.0);
+        if emulate_deposit(&flows, mid, value_day) < current_value {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Ok((low + high) / dec!(2.0) * dec!(100.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days_since_epoch_known_dates() {
+        assert_eq!(days_since_epoch("1970-01-01").unwrap(), 0);
+        assert_eq!(days_since_epoch("2000-03-01").unwrap(), 11017);
+        assert_eq!(days_since_epoch("2024-02-29").unwrap(), 19782);
+    }
+
+    #[test]
+    fn days_since_epoch_rejects_malformed_date() {
+        assert!(days_since_epoch("not-a-date").is_err());
+    }
+
+    #[test]
+    fn emulate_deposit_at_zero_rate_keeps_a_single_flow() {
+        let flows = [(0, dec!(100.0))];
+        let balance = emulate_deposit(&flows, dec!(0.0), 10);
+        assert_eq!(balance, dec!(100.0));
+    }
+
+    #[test]
+    fn emulate_deposit_credits_each_flow_on_its_own_day() {
+        let flows = [(0, dec!(100.0)), (5, dec!(50.0))];
+        let balance = emulate_deposit(&flows, dec!(0.0), 5);
+        assert_eq!(balance, dec!(150.0));
+    }
+}
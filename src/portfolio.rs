@@ -5,17 +5,106 @@ use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 use serde::Deserialize;
 use serde_json::Result;
+use std::collections::HashMap;
 use std::fs;
 use std::io::Read;
 
+use crate::currency::CurrencyConverter;
+use crate::performance;
+use crate::quotes::{self, QuotesConfig};
+
+// "auto" resolves the price from a QuoteProvider at load time.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum PriceSpec {
+    Fixed(Decimal),
+    Auto(String),
+}
+
+// A flat fee plus a percentage of the transaction value, clamped to [min, max].
+#[derive(Debug, Clone, Deserialize)]
+pub struct CommissionSpec {
+    #[serde(default)]
+    pub flat: Decimal,
+    #[serde(default)]
+    pub percentage: Decimal,
+    #[serde(default)]
+    pub min: Option<Decimal>,
+    #[serde(default)]
+    pub max: Option<Decimal>,
+}
+
+impl CommissionSpec {
+    fn calculate(&self, transaction_value: Decimal) -> Decimal {
+        let mut fee = self.flat + transaction_value * self.percentage / dec!(100.0);
+        if let Some(min) = self.min {
+            fee = fee.max(min);
+        }
+        if let Some(max) = self.max {
+            fee = fee.min(max);
+        }
+        fee
+    }
+}
+
+fn default_band() -> Decimal {
+    dec!(5.0)
+}
+
+fn default_lot_size() -> Decimal {
+    dec!(1.0)
+}
+
+fn default_currency() -> String {
+    "USD".to_string()
+}
+
+// date is ISO YYYY-MM-DD, so lexicographic order is chronological order.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Lot {
+    pub count: Decimal,
+    pub unit_cost: Decimal,
+    pub date: String,
+}
+
+// amount is positive for deposits, negative for withdrawals.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Cashflow {
+    pub date: String,
+    pub amount: Decimal,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct Asset {
     pub name: String,
-    pub price: Decimal,
+    #[serde(default, rename = "price")]
+    price_spec: Option<PriceSpec>,
     pub count: Decimal,
     pub alloc: Decimal,
+    #[serde(default)]
+    pub min_trade_volume: Option<Decimal>,
+    #[serde(default)]
+    pub commission: Option<CommissionSpec>,
+    #[serde(default)]
+    pub band: Option<Decimal>,
+    // currency price and commission are denominated in.
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    // when false, trades are rounded to the nearest lot_size.
+    #[serde(default)]
+    pub fractional: bool,
+    #[serde(default = "default_lot_size")]
+    pub lot_size: Decimal,
+    // oldest first, used for FIFO realized gains. empty means no gain reported.
+    #[serde(default)]
+    pub lots: Vec<Lot>,
+    #[serde(skip_deserializing)]
+    pub price: Decimal,
     #[serde(skip_deserializing)]
     pub value: Decimal,
+    // set by rebalance when this asset's drift was within its tolerance band.
+    #[serde(skip_deserializing)]
+    pub pinned: bool,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -23,6 +112,23 @@ pub struct Portfolio {
     pub assets: Vec<Asset>,
     #[serde(default)]
     pub donotsell: bool,
+    #[serde(default)]
+    pub quotes: Option<QuotesConfig>,
+    #[serde(default)]
+    pub min_trade_volume: Decimal,
+    #[serde(default)]
+    pub commission: Option<CommissionSpec>,
+    // percentage-point tolerance: within band of target is left untouched.
+    #[serde(default = "default_band")]
+    pub band: Decimal,
+    #[serde(default)]
+    pub cashflows: Vec<Cashflow>,
+    // currency every asset's value is converted into before allocations are computed.
+    #[serde(default = "default_currency")]
+    pub base_currency: String,
+    // 1 unit of the key currency, in base_currency. consulted before a live quote.
+    #[serde(default)]
+    pub rates: HashMap<String, Decimal>,
     #[serde(skip_deserializing)]
     pub value: Decimal,
 }
@@ -36,13 +142,16 @@ pub enum BuySell {
 #[derive(Debug, Clone, Deserialize)]
 pub struct Action {
     pub buysell: BuySell,
-    pub amount: u32,
+    pub amount: Decimal,
     pub name: String,
+    // currency, transaction_value, commission, and realized_gain are all in
+    // this asset's own currency, not the portfolio's base_currency.
+    pub currency: String,
     pub transaction_value: Decimal,
+    pub commission: Decimal,
+    pub realized_gain: Option<Decimal>,
 }
 
-static CURRENCY: &str = "USD";
-
 impl Portfolio {
     fn get_allocation_sum(&self) -> Decimal {
         let mut sum: Decimal = dec!(0.0);
@@ -58,7 +167,7 @@ impl Portfolio {
 
     fn get_currency(&self) -> Option<Asset> {
         for a in &self.assets {
-            if a.name == CURRENCY {
+            if a.name == self.base_currency {
                 return Some(a.clone());
             }
         }
@@ -69,11 +178,117 @@ impl Portfolio {
         self.get_currency().is_some()
     }
 
-    fn calculate_asset_values(&mut self) {
+    fn has_valid_lot_sizes(&self) -> bool {
+        self.assets.iter().all(|a| a.lot_size > dec!(0.0))
+    }
+
+    fn min_trade_volume_for(&self, a: &Asset) -> Decimal {
+        a.min_trade_volume.unwrap_or(self.min_trade_volume)
+    }
+
+    fn commission_for(&self, a: &Asset, transaction_value: Decimal) -> Decimal {
+        a.commission
+            .as_ref()
+            .or(self.commission.as_ref())
+            .map_or(dec!(0.0), |spec| spec.calculate(transaction_value))
+    }
+
+    pub fn currency_converter(&self) -> anyhow::Result<CurrencyConverter> {
+        let provider = quotes::resolve_provider(self.quotes.as_ref())?;
+        Ok(CurrencyConverter::new(
+            self.base_currency.clone(),
+            self.rates.clone(),
+            provider,
+        ))
+    }
+
+    fn realized_gain_for_sell(&self, a: &Asset, sold_qty: Decimal) -> Option<Decimal> {
+        if a.lots.is_empty() {
+            return None;
+        }
+        let mut lots = a.lots.clone();
+        lots.sort_by(|x, y| x.date.cmp(&y.date));
+        let mut remaining = sold_qty;
+        let mut cost_basis = dec!(0.0);
+        for lot in &lots {
+            if remaining <= dec!(0.0) {
+                break;
+            }
+            let matched = remaining.min(lot.count);
+            cost_basis += matched * lot.unit_cost;
+            remaining -= matched;
+        }
+        if remaining > dec!(0.0) {
+            // lots don't cover the whole sale; the cost basis would be
+            // understated, so don't report a gain at all
+            return None;
+        }
+        let proceeds = sold_qty * a.price;
+        Some(proceeds - cost_basis)
+    }
+
+    // shared by get_actions and estimate_total_commission so both agree on
+    // what actually trades.
+    fn planned_trade(&self, a: &Asset, b: &Asset) -> Option<(Decimal, Decimal, Decimal)> {
+        let raw_diff: Decimal = b.count - a.count;
+        let diff: Decimal = if a.fractional {
+            raw_diff
+        } else {
+            (raw_diff / a.lot_size).round() * a.lot_size
+        };
+        if diff == dec!(0) {
+            return None;
+        }
+        let transaction_value: Decimal = (diff * a.price).abs();
+        if transaction_value < self.min_trade_volume_for(a) {
+            // too small a trade to bother with, e.g. it wouldn't clear the broker's minimum
+            return None;
+        }
+        let commission = self.commission_for(a, transaction_value);
+        Some((diff, transaction_value, commission))
+    }
+
+    fn estimate_total_commission(
+        &self,
+        target_portfolio: &Self,
+        converter: &CurrencyConverter,
+    ) -> anyhow::Result<Decimal> {
+        let mut total = dec!(0.0);
+        for (a, b) in self.assets.iter().zip(target_portfolio.assets.iter()) {
+            if a.name == self.base_currency {
+                continue;
+            }
+            let Some((_, _, commission)) = self.planned_trade(a, b) else {
+                continue;
+            };
+            total += converter.to_base(&a.currency, commission)?;
+        }
+        Ok(total)
+    }
+
+    fn resolve_prices(&mut self) -> anyhow::Result<()> {
+        let provider = quotes::resolve_provider(self.quotes.as_ref())?;
+        for a in &mut self.assets {
+            a.price = match &a.price_spec {
+                Some(PriceSpec::Fixed(p)) => *p,
+                Some(PriceSpec::Auto(_)) | None => {
+                    let provider = provider
+                        .as_deref()
+                        .ok_or_else(|| anyhow!("{} needs an auto-resolved price but no [quotes] section or QUOTE_PROVIDER env var is configured", a.name))?;
+                    quotes::resolve_quote(provider, &a.name)?
+                }
+            };
+        }
+        Ok(())
+    }
+
+    fn calculate_asset_values(&mut self, converter: &CurrencyConverter) -> anyhow::Result<()> {
+        self.value = dec!(0.0);
         for a in &mut self.assets {
-            a.value = a.price * (a.count);
+            a.value = converter.to_base(&a.currency, a.price * a.count)?;
             self.value += a.value;
         }
+        Ok(())
     }
 
     fn recalc_allocation(&mut self) {
@@ -82,41 +297,84 @@ impl Portfolio {
         }
     }
 
-    pub fn rebalance(&self) -> Self {
+    fn current_alloc_pct(&self, a: &Asset) -> Decimal {
+        if self.value == dec!(0.0) {
+            dec!(0.0)
+        } else {
+            a.value / self.value * dec!(100.0)
+        }
+    }
+
+    fn is_within_band(&self, a: &Asset) -> bool {
+        if a.name == self.base_currency {
+            return false;
+        }
+        let band = a.band.unwrap_or(self.band);
+        (self.current_alloc_pct(a) - a.alloc).abs() <= band
+    }
+
+    pub fn rebalance(&self, converter: &CurrencyConverter) -> anyhow::Result<Self> {
         let mut target_portfolio = self.clone();
+        // Assets within their tolerance band are pinned at their current
+        // count; the rest share whatever cash that frees up (or needs),
+        // proportionally to their target allocation among themselves.
+        let pinned_value: Decimal = self
+            .assets
+            .iter()
+            .filter(|a| self.is_within_band(a))
+            .map(|a| a.value)
+            .sum();
+        let rebalanced_alloc_sum: Decimal = self
+            .assets
+            .iter()
+            .filter(|a| !self.is_within_band(a))
+            .map(|a| a.alloc)
+            .sum();
+        let rebalanceable_value = self.value - pinned_value;
         for a in &mut target_portfolio.assets {
-            a.count = (target_portfolio.value * a.alloc / dec!(100.0)) / a.price;
-            a.value = a.price * a.count;
+            if self.is_within_band(a) {
+                a.pinned = true;
+            } else if rebalanced_alloc_sum != dec!(0.0) {
+                let price_in_base = converter.to_base(&a.currency, a.price)?;
+                a.count = (rebalanceable_value * a.alloc / rebalanced_alloc_sum) / price_in_base;
+                a.value = price_in_base * a.count;
+            }
+            // Else every non-pinned asset targets 0% (e.g. a fully invested,
+            // in-tolerance portfolio with no cash target): there's nothing
+            // to spread, so leave this asset at its current count.
         }
         target_portfolio.value = dec!(0.0);
         for a in &target_portfolio.assets {
             target_portfolio.value += a.value;
         }
-        // add leftover to currency
-        if self.value > target_portfolio.value {
-            for a in &mut target_portfolio.assets {
-                // TODO improve this to make it generic
-                if a.name == CURRENCY {
-                    a.count = self.value - target_portfolio.value;
-                    a.value = a.price * (a.count);
-                    target_portfolio.value += a.value;
-                    break;
-                }
+        // Reserve the commissions the rebalance trades will incur out of
+        // the base-currency asset, unconditionally: the proportional
+        // allocation above already accounts for the whole portfolio value,
+        // so there's no other leftover to sweep.
+        let total_commission = self.estimate_total_commission(&target_portfolio, converter)?;
+        for a in &mut target_portfolio.assets {
+            if a.name == self.base_currency {
+                let price_in_base = converter.to_base(&a.currency, a.price)?;
+                a.value -= total_commission;
+                a.count = a.value / price_in_base;
+                target_portfolio.value -= total_commission;
+                break;
             }
         }
         target_portfolio.recalc_allocation();
-        target_portfolio
+        Ok(target_portfolio)
     }
 
-    pub fn add_without_selling(&self) -> anyhow::Result<Self> {
+    pub fn add_without_selling(&self, converter: &CurrencyConverter) -> anyhow::Result<Self> {
         let mut target_portfolio = self.clone();
         let currency = self
             .get_currency()
             .ok_or_else(|| anyhow!("cannot get currency"))?;
         for a in &mut target_portfolio.assets {
-            a.count += (currency.value * a.alloc / dec!(100.0)) / a.price;
-            a.value = a.price * a.count;
-            if a.name == CURRENCY {
+            let price_in_base = converter.to_base(&a.currency, a.price)?;
+            a.count += (currency.value * a.alloc / dec!(100.0)) / price_in_base;
+            a.value = converter.to_base(&a.currency, a.price * a.count)?;
+            if a.name == self.base_currency {
                 a.count = dec!(0.0);
                 a.value = dec!(0.0);
             }
@@ -135,32 +393,51 @@ impl Portfolio {
             let a = &self.assets[i];
             let b = &target_portfolio.assets[i];
             assert!(a.name == b.name);
-            let diff: Decimal = b.count - a.count;
-            let transaction_value: Decimal = (diff * a.price).abs();
-            match diff {
-                d if d == dec!(0) => {
-                    // Nothing
-                }
-                d if d > dec!(0) => ret.push(Action {
+            let Some((diff, transaction_value, commission)) = self.planned_trade(a, b) else {
+                continue;
+            };
+            if diff > dec!(0) {
+                ret.push(Action {
                     buysell: BuySell::Buy,
-                    amount: d.to_u32().ok_or_else(|| anyhow!("cannot format {a:?}"))?,
+                    amount: diff,
                     name: a.name.clone(),
+                    currency: a.currency.clone(),
                     transaction_value,
-                }),
-                d if d < dec!(0) => ret.push(Action {
+                    commission,
+                    realized_gain: None,
+                });
+            } else {
+                ret.push(Action {
                     buysell: BuySell::Sell,
-                    amount: u32::try_from(
-                        -d.to_i32().ok_or_else(|| anyhow!("cannot format {a:?}"))?,
-                    )?,
+                    amount: -diff,
                     name: a.name.clone(),
+                    currency: a.currency.clone(),
                     transaction_value,
-                }),
-                _ => {}
+                    commission,
+                    realized_gain: self.realized_gain_for_sell(a, -diff),
+                });
             }
         }
         Ok(ret)
     }
 
+    pub fn annualized_return(&self) -> anyhow::Result<Decimal> {
+        performance::annualized_return(&self.cashflows, self.value)
+    }
+
+    pub fn total_contributions(&self) -> Decimal {
+        self.cashflows
+            .iter()
+            .map(|c| c.amount)
+            .filter(|a| *a > dec!(0.0))
+            .sum()
+    }
+
+    pub fn absolute_gain(&self) -> Decimal {
+        let net_invested: Decimal = self.cashflows.iter().map(|c| c.amount).sum();
+        self.value - net_invested
+    }
+
     pub fn get_display_data(&mut self) -> anyhow::Result<Vec<(&str, u64)>> {
         self.recalc_allocation();
         let mut display_data: Vec<(&str, u64)> = Vec::new();
@@ -192,11 +469,269 @@ pub fn load_portfolio_from_file(port_file: &str) -> anyhow::Result<Portfolio> {
                 ));
             }
             if !p.has_currency() {
-                return Err(anyhow!("Your portfolio doesn't have a {CURRENCY} asset"));
+                return Err(anyhow!(
+                    "Your portfolio doesn't have a {} asset",
+                    p.base_currency
+                ));
             }
-            p.calculate_asset_values();
+            if !p.has_valid_lot_sizes() {
+                return Err(anyhow!("Every asset's lot_size must be greater than 0"));
+            }
+            p.resolve_prices()?;
+            let converter = p.currency_converter()?;
+            p.calculate_asset_values(&converter)?;
             Ok(p)
         }
         Err(e) => Err(anyhow!("Error parsing the portfolio json {e:?}")),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset_with_lots(lots: Vec<Lot>) -> Asset {
+        Asset {
+            name: "AAA".to_string(),
+            price_spec: None,
+            count: dec!(0.0),
+            alloc: dec!(0.0),
+            min_trade_volume: None,
+            commission: None,
+            band: None,
+            currency: default_currency(),
+            fractional: true,
+            lot_size: default_lot_size(),
+            lots,
+            price: dec!(10.0),
+            value: dec!(0.0),
+            pinned: false,
+        }
+    }
+
+    fn empty_portfolio() -> Portfolio {
+        Portfolio {
+            assets: Vec::new(),
+            donotsell: false,
+            quotes: None,
+            min_trade_volume: dec!(0.0),
+            commission: None,
+            band: default_band(),
+            cashflows: Vec::new(),
+            base_currency: default_currency(),
+            rates: HashMap::new(),
+            value: dec!(0.0),
+        }
+    }
+
+    fn lot(count: Decimal, unit_cost: Decimal, date: &str) -> Lot {
+        Lot {
+            count,
+            unit_cost,
+            date: date.to_string(),
+        }
+    }
+
+    #[test]
+    fn realized_gain_with_no_lots_is_none() {
+        let p = empty_portfolio();
+        let a = asset_with_lots(Vec::new());
+        assert_eq!(p.realized_gain_for_sell(&a, dec!(5.0)), None);
+    }
+
+    #[test]
+    fn realized_gain_matches_lots_fifo() {
+        let p = empty_portfolio();
+        let a = asset_with_lots(vec![
+            lot(dec!(5.0), dec!(4.0), "2024-01-01"),
+            lot(dec!(5.0), dec!(6.0), "2024-02-01"),
+        ]);
+        // sell 7 @ price 10: 5 from the Jan lot (cost 4) + 2 from the Feb lot (cost 6)
+        let gain = p.realized_gain_for_sell(&a, dec!(7.0)).unwrap();
+        assert_eq!(gain, dec!(7.0) * dec!(10.0) - (dec!(5.0) * dec!(4.0) + dec!(2.0) * dec!(6.0)));
+    }
+
+    #[test]
+    fn realized_gain_is_none_when_lots_dont_cover_the_sale() {
+        let p = empty_portfolio();
+        let a = asset_with_lots(vec![lot(dec!(3.0), dec!(4.0), "2024-01-01")]);
+        assert_eq!(p.realized_gain_for_sell(&a, dec!(5.0)), None);
+    }
+
+    #[test]
+    fn commission_is_flat_plus_percentage() {
+        let spec = CommissionSpec {
+            flat: dec!(1.0),
+            percentage: dec!(1.0),
+            min: None,
+            max: None,
+        };
+        assert_eq!(spec.calculate(dec!(1000.0)), dec!(11.0));
+    }
+
+    #[test]
+    fn commission_is_clamped_to_min_and_max() {
+        let spec = CommissionSpec {
+            flat: dec!(0.0),
+            percentage: dec!(1.0),
+            min: Some(dec!(5.0)),
+            max: Some(dec!(20.0)),
+        };
+        assert_eq!(spec.calculate(dec!(100.0)), dec!(5.0));
+        assert_eq!(spec.calculate(dec!(10000.0)), dec!(20.0));
+    }
+
+    #[test]
+    fn within_band_when_drift_at_or_under_tolerance() {
+        let mut p = empty_portfolio();
+        p.band = dec!(5.0);
+        p.value = dec!(100.0);
+        let mut a = asset_with_lots(Vec::new());
+        a.alloc = dec!(50.0);
+        a.value = dec!(54.0);
+        assert!(p.is_within_band(&a));
+    }
+
+    #[test]
+    fn outside_band_when_drift_exceeds_tolerance() {
+        let mut p = empty_portfolio();
+        p.band = dec!(5.0);
+        p.value = dec!(100.0);
+        let mut a = asset_with_lots(Vec::new());
+        a.alloc = dec!(50.0);
+        a.value = dec!(56.0);
+        assert!(!p.is_within_band(&a));
+    }
+
+    #[test]
+    fn base_currency_asset_is_never_pinned() {
+        let mut p = empty_portfolio();
+        p.value = dec!(100.0);
+        let mut a = asset_with_lots(Vec::new());
+        a.name = p.base_currency.clone();
+        a.alloc = a.value;
+        assert!(!p.is_within_band(&a));
+    }
+
+    fn asset(name: &str, currency: &str, price: Decimal, count: Decimal, alloc: Decimal) -> Asset {
+        Asset {
+            name: name.to_string(),
+            price_spec: None,
+            count,
+            alloc,
+            min_trade_volume: None,
+            commission: None,
+            band: None,
+            currency: currency.to_string(),
+            fractional: true,
+            lot_size: default_lot_size(),
+            lots: Vec::new(),
+            price,
+            value: price * count,
+            pinned: false,
+        }
+    }
+
+    fn portfolio_with(assets: Vec<Asset>) -> Portfolio {
+        let mut p = empty_portfolio();
+        p.value = assets.iter().map(|a| a.value).sum();
+        p.assets = assets;
+        p
+    }
+
+    #[test]
+    fn rebalance_spreads_value_by_target_allocation() {
+        let p = portfolio_with(vec![
+            asset("USD", "USD", dec!(1.0), dec!(500.0), dec!(0.0)),
+            asset("AAA", "USD", dec!(10.0), dec!(25.0), dec!(50.0)),
+            asset("BBB", "USD", dec!(20.0), dec!(12.5), dec!(50.0)),
+        ]);
+        let converter = CurrencyConverter::new("USD".to_string(), HashMap::new(), None);
+        let target = p.rebalance(&converter).unwrap();
+
+        assert!(!target.assets[0].pinned);
+        assert_eq!(target.assets[0].value, dec!(0.0));
+        assert_eq!(target.assets[1].value, dec!(500.0));
+        assert_eq!(target.assets[1].count, dec!(50.0));
+        assert_eq!(target.assets[2].value, dec!(500.0));
+        assert_eq!(target.assets[2].count, dec!(25.0));
+        assert_eq!(target.value, dec!(1000.0));
+    }
+
+    #[test]
+    fn rebalance_pins_in_band_assets_and_reserves_commission_from_cash() {
+        let mut aaa = asset("AAA", "USD", dec!(10.0), dec!(25.0), dec!(50.0));
+        aaa.band = Some(dec!(30.0));
+        let mut p = portfolio_with(vec![
+            asset("USD", "USD", dec!(1.0), dec!(500.0), dec!(0.0)),
+            aaa,
+            asset("BBB", "USD", dec!(20.0), dec!(12.5), dec!(50.0)),
+        ]);
+        p.commission = Some(CommissionSpec {
+            flat: dec!(0.0),
+            percentage: dec!(1.0),
+            min: None,
+            max: None,
+        });
+        let converter = CurrencyConverter::new("USD".to_string(), HashMap::new(), None);
+        let target = p.rebalance(&converter).unwrap();
+
+        // AAA's drift (25%) is within its 30-point band, so it's pinned...
+        assert!(target.assets[1].pinned);
+        assert_eq!(target.assets[1].count, dec!(25.0));
+        // ...and BBB alone absorbs the rebalanceable value.
+        assert!(!target.assets[2].pinned);
+        assert_eq!(target.assets[2].count, dec!(37.5));
+        assert_eq!(target.assets[2].value, dec!(750.0));
+        // BBB's trade (750 - 250 = 500 @ 1%) reserves a 5.0 commission out of cash.
+        assert_eq!(target.assets[0].value, dec!(-5.0));
+        assert_eq!(target.value, dec!(995.0));
+    }
+
+    #[test]
+    fn get_actions_rounds_lots_drops_subminimum_trades_and_reports_gains() {
+        let mut ccc = asset("CCC", "USD", dec!(5.0), dec!(100.0), dec!(0.0));
+        ccc.fractional = false;
+        ccc.lot_size = dec!(10.0);
+        let mut ddd = asset("DDD", "USD", dec!(20.0), dec!(50.0), dec!(0.0));
+        ddd.fractional = false;
+        ddd.lot_size = dec!(10.0);
+        ddd.min_trade_volume = Some(dec!(50.0));
+        ddd.commission = Some(CommissionSpec {
+            flat: dec!(2.0),
+            percentage: dec!(0.0),
+            min: None,
+            max: None,
+        });
+        let mut eee = asset("EEE", "USD", dec!(10.0), dec!(50.0), dec!(0.0));
+        eee.lots = vec![lot(dec!(20.0), dec!(8.0), "2024-01-01")];
+
+        let mut original = portfolio_with(vec![
+            asset("USD", "USD", dec!(1.0), dec!(1000.0), dec!(0.0)),
+            ccc,
+            ddd,
+            eee,
+        ]);
+        original.min_trade_volume = dec!(100.0);
+
+        let mut target = original.clone();
+        target.assets[1].count = dec!(107.0); // CCC: raw diff 7 -> rounds to a 10-lot, but $50 < $100 min
+        target.assets[2].count = dec!(67.0); // DDD: raw diff 17 -> rounds to a 20-unit (2-lot) trade
+        target.assets[3].count = dec!(30.0); // EEE: sell 20 units
+
+        let actions = original.get_actions(&target).unwrap();
+
+        assert_eq!(actions.len(), 2);
+        assert_eq!(actions[0].name, "DDD");
+        assert!(matches!(actions[0].buysell, BuySell::Buy));
+        assert_eq!(actions[0].amount, dec!(20.0));
+        assert_eq!(actions[0].transaction_value, dec!(400.0));
+        assert_eq!(actions[0].commission, dec!(2.0));
+        assert_eq!(actions[0].realized_gain, None);
+
+        assert_eq!(actions[1].name, "EEE");
+        assert!(matches!(actions[1].buysell, BuySell::Sell));
+        assert_eq!(actions[1].amount, dec!(20.0));
+        assert_eq!(actions[1].realized_gain, Some(dec!(40.0)));
+    }
+}
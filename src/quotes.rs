@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::anyhow;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+const CACHE_FILE: &str = ".quote_cache.json";
+const SECONDS_PER_DAY: u64 = 60 * 60 * 24;
+
+pub trait QuoteProvider {
+    fn fetch_quote(&self, symbol: &str) -> anyhow::Result<Decimal>;
+
+    fn name(&self) -> &'static str;
+
+    // Default assumes the concatenated symbol (e.g. "EURUSD") works as a
+    // regular quote; override where a provider's quote endpoint can't serve
+    // FX pairs that way.
+    fn fetch_fx_rate(&self, from: &str, to: &str) -> anyhow::Result<Decimal> {
+        self.fetch_quote(&format!("{from}{to}"))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct QuotesConfig {
+    pub provider: String,
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+}
+
+pub struct AlphaVantageProvider {
+    api_key: String,
+}
+
+pub struct FinnhubProvider {
+    api_key: String,
+}
+
+pub struct TwelveDataProvider {
+    api_key: String,
+}
+
+impl QuoteProvider for AlphaVantageProvider {
+    fn fetch_quote(&self, symbol: &str) -> anyhow::Result<Decimal> {
+        let url = format!(
+            "https://www.alphavantage.co/query?function=GLOBAL_QUOTE&symbol={symbol}&apikey={}",
+            self.api_key
+        );
+        let body: serde_json::Value = reqwest::blocking::get(url)?.json()?;
+        let price = body["Global Quote"]["05. price"]
+            .as_str()
+            .ok_or_else(|| anyhow!("no price in AlphaVantage response for {symbol}"))?;
+        Ok(price.parse()?)
+    }
+
+    fn name(&self) -> &'static str {
+        "alphavantage"
+    }
+
+    fn fetch_fx_rate(&self, from: &str, to: &str) -> anyhow::Result<Decimal> {
+        let url = format!(
+            "https://www.alphavantage.co/query?function=CURRENCY_EXCHANGE_RATE&from_currency={from}&to_currency={to}&apikey={}",
+            self.api_key
+        );
+        let body: serde_json::Value = reqwest::blocking::get(url)?.json()?;
+        let rate = body["Realtime Currency Exchange Rate"]["5. Exchange Rate"]
+            .as_str()
+            .ok_or_else(|| anyhow!("no rate in AlphaVantage response for {from}{to}"))?;
+        Ok(rate.parse()?)
+    }
+}
+
+impl QuoteProvider for FinnhubProvider {
+    fn fetch_quote(&self, symbol: &str) -> anyhow::Result<Decimal> {
+        let url = format!("https://finnhub.io/api/v1/quote?symbol={symbol}&token={}", self.api_key);
+        let body: serde_json::Value = reqwest::blocking::get(url)?.json()?;
+        let price = body["c"]
+            .as_f64()
+            .ok_or_else(|| anyhow!("no price in Finnhub response for {symbol}"))?;
+        Decimal::try_from(price).map_err(|e| anyhow!("cannot convert Finnhub price: {e:?}"))
+    }
+
+    fn name(&self) -> &'static str {
+        "finnhub"
+    }
+
+    fn fetch_fx_rate(&self, from: &str, to: &str) -> anyhow::Result<Decimal> {
+        self.fetch_quote(&format!("OANDA:{from}_{to}"))
+    }
+}
+
+impl QuoteProvider for TwelveDataProvider {
+    fn fetch_quote(&self, symbol: &str) -> anyhow::Result<Decimal> {
+        let url = format!("https://api.twelvedata.com/price?symbol={symbol}&apikey={}", self.api_key);
+        let body: serde_json::Value = reqwest::blocking::get(url)?.json()?;
+        let price = body["price"]
+            .as_str()
+            .ok_or_else(|| anyhow!("no price in TwelveData response for {symbol}"))?;
+        Ok(price.parse()?)
+    }
+
+    fn name(&self) -> &'static str {
+        "twelvedata"
+    }
+}
+
+fn api_key_for(config: &QuotesConfig) -> anyhow::Result<String> {
+    let env_var = config
+        .api_key_env
+        .clone()
+        .unwrap_or_else(|| format!("{}_API_KEY", config.provider.to_uppercase()));
+    std::env::var(&env_var).map_err(|_| anyhow!("missing API key in env var {env_var}"))
+}
+
+pub fn get_provider(config: &QuotesConfig) -> anyhow::Result<Box<dyn QuoteProvider>> {
+    let api_key = api_key_for(config)?;
+    match config.provider.to_lowercase().as_str() {
+        "alphavantage" => Ok(Box::new(AlphaVantageProvider { api_key })),
+        "finnhub" => Ok(Box::new(FinnhubProvider { api_key })),
+        "twelvedata" => Ok(Box::new(TwelveDataProvider { api_key })),
+        other => Err(anyhow!("unknown quote provider {other}")),
+    }
+}
+
+// Falls back to the QUOTE_PROVIDER env var when there's no [quotes] section.
+pub fn resolve_provider(config: Option<&QuotesConfig>) -> anyhow::Result<Option<Box<dyn QuoteProvider>>> {
+    if let Some(config) = config {
+        return Ok(Some(get_provider(config)?));
+    }
+    match std::env::var("QUOTE_PROVIDER") {
+        Ok(provider) => Ok(Some(get_provider(&QuotesConfig {
+            provider,
+            api_key_env: None,
+        })?)),
+        Err(_) => Ok(None),
+    }
+}
+
+fn today() -> anyhow::Result<u64> {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    Ok(secs / SECONDS_PER_DAY)
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Cache {
+    // keyed by "provider:symbol:day"
+    entries: HashMap<String, Decimal>,
+}
+
+fn load_cache() -> Cache {
+    let Ok(mut f) = fs::File::open(CACHE_FILE) else {
+        return Cache::default();
+    };
+    let mut contents = String::new();
+    if f.read_to_string(&mut contents).is_err() {
+        return Cache::default();
+    }
+    serde_json::from_str(&contents).unwrap_or_default()
+}
+
+fn save_cache(cache: &Cache) {
+    if let Ok(serialized) = serde_json::to_string(cache) {
+        let _ = fs::write(CACHE_FILE, serialized);
+    }
+}
+
+fn resolve_cached(cache_key: String, fetch: impl FnOnce() -> anyhow::Result<Decimal>) -> anyhow::Result<Decimal> {
+    let mut cache = load_cache();
+    if let Some(value) = cache.entries.get(&cache_key) {
+        return Ok(*value);
+    }
+    let value = fetch()?;
+    cache.entries.insert(cache_key, value);
+    save_cache(&cache);
+    Ok(value)
+}
+
+// Caches the result on disk for the rest of the day so repeated runs don't
+// hammer the API.
+pub fn resolve_quote(provider: &dyn QuoteProvider, symbol: &str) -> anyhow::Result<Decimal> {
+    let day = today()?;
+    let cache_key = format!("{}:{symbol}:{day}", provider.name());
+    resolve_cached(cache_key, || provider.fetch_quote(symbol))
+}
+
+// Same on-disk cache as resolve_quote, keyed separately so an FX pair can't
+// collide with a like-named equity symbol.
+pub fn resolve_fx_rate(provider: &dyn QuoteProvider, from: &str, to: &str) -> anyhow::Result<Decimal> {
+    let day = today()?;
+    let cache_key = format!("{}:fx:{from}{to}:{day}", provider.name());
+    resolve_cached(cache_key, || provider.fetch_fx_rate(from, to))
+}